@@ -0,0 +1,126 @@
+//! Lets a developer pause the fixed-timestep physics schedule and single-step through it.
+//! Only compiled in when the `debug_stepping` feature is enabled.
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+
+use crate::TEXT_COLOR;
+
+// Order mirrors the indices passed to `system_run_criteria` in `main`'s fixed-timestep schedule.
+pub const STEPPED_SYSTEMS: &[&str] = &[
+    "move_paddle",
+    "move_paddle_by_mouse",
+    "stick_ball_to_paddle",
+    "handle_waiting_click",
+    "apply_velocity",
+    "check_for_collisions",
+    "play_collision_sounds",
+    "update_scoreboard",
+    "check_win_condition",
+    "check_lose_condition",
+];
+
+#[derive(Default)]
+pub struct SteppingState {
+    pub enabled: bool,
+    pub cursor: usize,
+}
+
+#[derive(Component)]
+pub struct SteppingOverlayText;
+
+pub fn spawn_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: String::new(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.,
+                        color: TEXT_COLOR,
+                    },
+                }],
+                ..default()
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(10.),
+                    left: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SteppingOverlayText);
+}
+
+pub fn handle_input(keyboard_input: Res<Input<KeyCode>>, mut stepping: ResMut<SteppingState>) {
+    if keyboard_input.just_pressed(KeyCode::F10) {
+        stepping.enabled = !stepping.enabled;
+        stepping.cursor = 0;
+    }
+}
+
+pub fn update_overlay(
+    stepping: Res<SteppingState>,
+    mut query: Query<&mut Text, With<SteppingOverlayText>>,
+) {
+    let mut text = query.single_mut();
+    if !stepping.enabled {
+        text.sections[0].value.clear();
+        return;
+    }
+
+    let lines: Vec<String> = STEPPED_SYSTEMS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if i == stepping.cursor {
+                format!("> {}", name)
+            } else {
+                format!("  {}", name)
+            }
+        })
+        .collect();
+    text.sections[0].value = format!(
+        "STEPPING (F10 toggle, F11 step system, F12 step frame)\n{}",
+        lines.join("\n")
+    );
+}
+
+/// Chained onto a single system's `FixedTimestep::step` run criteria. `index` must match this
+/// system's position in `STEPPED_SYSTEMS`. Read-only: F12 lets every system run (a full frame),
+/// F11 only lets the system whose index matches the current cursor run. Advancing the cursor
+/// is left to `advance_cursor`, so evaluation order between these per-system criteria doesn't
+/// matter.
+pub fn system_run_criteria(
+    index: usize,
+) -> impl FnMut(In<ShouldRun>, Res<Input<KeyCode>>, Res<SteppingState>) -> ShouldRun {
+    move |In(should_run): In<ShouldRun>, keyboard_input: Res<Input<KeyCode>>, stepping: Res<SteppingState>| {
+        if !matches!(should_run, ShouldRun::Yes | ShouldRun::YesAndCheckAgain) {
+            return should_run;
+        }
+
+        if !stepping.enabled {
+            return should_run;
+        }
+
+        if keyboard_input.just_pressed(KeyCode::F12) {
+            return should_run;
+        }
+
+        if keyboard_input.just_pressed(KeyCode::F11) && stepping.cursor == index {
+            return should_run;
+        }
+
+        ShouldRun::No
+    }
+}
+
+/// Runs once per fixed-timestep tick after all stepped systems; the sole writer of `cursor`.
+pub fn advance_cursor(keyboard_input: Res<Input<KeyCode>>, mut stepping: ResMut<SteppingState>) {
+    if stepping.enabled && keyboard_input.just_pressed(KeyCode::F11) {
+        stepping.cursor = (stepping.cursor + 1) % STEPPED_SYSTEMS.len();
+    }
+}
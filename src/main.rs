@@ -1,10 +1,14 @@
 use bevy::{
+    audio::PlaybackSettings,
     core::FixedTimestep,
+    ecs::schedule::ShouldRun,
     math::{const_vec2, const_vec3},
     prelude::*,
-    sprite::collide_aabb::{collide, Collision},
 };
 
+#[cfg(feature = "debug_stepping")]
+mod debug_stepping;
+
 // Game and canvas
 const TIME_STEP: f32 = 1.0 / 60.0;
 const CANVAS_WIDTH: f32 = 1000.;
@@ -16,6 +20,7 @@ const PADDLE_SIZE: Vec3 = const_vec3!([120.0, 20.0, 0.0]);
 const PADDLE_SPEED: f32 = 800.0;
 const PADDLE_PADDING: f32 = 20.0;
 const PADDLE_COLOR: Color = Color::rgb(173. / 255., 186. / 255., 199. / 255.);
+const PADDLE_Y: f32 = BOTTOM_WALL + 30.0;
 
 // Ball
 const BALL_STARTING_POSITION: Vec3 = const_vec3!([0.0, -50.0, 1.0]);
@@ -35,6 +40,10 @@ const WALL_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
 
 // Bricks
 const BRICK_SIZE: Vec2 = const_vec2!([70., 25.]);
+const GAP_BETWEEN_BRICKS: f32 = 5.0;
+const GAP_BETWEEN_BRICKS_AND_SIDES: f32 = 40.0;
+const GAP_BETWEEN_PADDLE_AND_BRICKS: f32 = 540.0;
+const GAP_BETWEEN_BRICKS_AND_CEILING: f32 = 40.0;
 
 // Text
 const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
@@ -42,8 +51,19 @@ const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
 #[derive(Component)]
 struct Collider;
 
-#[derive(Default)]
-struct CollisionEvent;
+// What kind of collider the ball hit, so `play_collision_sounds` can pick a clip.
+enum CollisionSoundKind {
+    Brick,
+    Paddle,
+    Wall,
+}
+
+struct CollisionEvent(CollisionSoundKind);
+
+struct CollisionSounds {
+    brick_break: Handle<AudioSource>,
+    bounce: Handle<AudioSource>,
+}
 
 #[derive(Component, Deref, DerefMut)]
 struct Velocity(Vec2);
@@ -76,6 +96,13 @@ enum WallLocation {
     Top,
 }
 
+enum Collision {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
 fn wall_sprite_bundle(location: WallLocation) -> SpriteBundle {
     let pos = match location {
         WallLocation::Left => Vec2::new(LEFT_WALL, 0.),
@@ -109,6 +136,13 @@ struct GameState {
     lives: usize,
 }
 
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+enum AppState {
+    Playing,
+    GameOver,
+    Won,
+}
+
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut windows: ResMut<Windows>) {
     // Cameras
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
@@ -118,13 +152,19 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut windows: Re
     let window = windows.get_primary_mut().unwrap();
     window.set_resolution(CANVAS_WIDTH, CANVAS_HEIGHT);
 
+    // Sounds
+    commands.insert_resource(CollisionSounds {
+        brick_break: asset_server.load("sounds/brick_break.ogg"),
+        bounce: asset_server.load("sounds/bounce.ogg"),
+    });
+
     // Paddle
     commands
         .spawn()
         .insert(Paddle)
         .insert_bundle(SpriteBundle {
             transform: Transform {
-                translation: Vec3::new(0.0, BOTTOM_WALL + 30., 0.0),
+                translation: Vec3::new(0.0, PADDLE_Y, 0.0),
                 scale: PADDLE_SIZE,
                 ..default()
             },
@@ -159,6 +199,11 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut windows: Re
         .insert_bundle(wall_sprite_bundle(WallLocation::Top))
         .insert(Collider);
 
+    spawn_playfield(&mut commands, &asset_server);
+}
+
+// Spawns the ball, brick grid and scoreboard/game-over text; torn down and respawned on restart.
+fn spawn_playfield(commands: &mut Commands, asset_server: &AssetServer) {
     // Ball
     commands
         .spawn()
@@ -186,22 +231,45 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut windows: Re
         Color::rgb(34. / 255., 197. / 255., 94. / 255.),
         Color::rgb(6. / 255., 182. / 255., 212. / 255.),
     ];
-    for row_x in 0..12 {
-        for row_y in 0..6 {
+    // Count the brick columns/rows (each brick plus one gap) that fit between the walls/paddle/ceiling,
+    // then center the grid horizontally.
+    let total_width_of_bricks = (RIGHT_WALL - LEFT_WALL) - 2. * GAP_BETWEEN_BRICKS_AND_SIDES;
+    let bottom_edge_of_bricks = PADDLE_Y + GAP_BETWEEN_PADDLE_AND_BRICKS;
+    let total_height_of_bricks = TOP_WALL - bottom_edge_of_bricks - GAP_BETWEEN_BRICKS_AND_CEILING;
+
+    let n_columns = (total_width_of_bricks / (BRICK_SIZE.x + GAP_BETWEEN_BRICKS)).floor() as usize;
+    let n_rows = (total_height_of_bricks / (BRICK_SIZE.y + GAP_BETWEEN_BRICKS)).floor() as usize;
+    // If the arena is too narrow for even one brick, there's nothing to space out or spawn.
+    let n_vertical_gaps = n_columns.saturating_sub(1);
+
+    // The rounded column count rarely fills the allocated width exactly, so center the
+    // columns within it. Rows start at `bottom_edge_of_bricks`; leftover height stays above them.
+    let center_of_bricks = (LEFT_WALL + RIGHT_WALL) / 2.0;
+    let left_edge_of_bricks = center_of_bricks
+        - (n_columns as f32 / 2.0 * BRICK_SIZE.x)
+        - n_vertical_gaps as f32 / 2.0 * GAP_BETWEEN_BRICKS;
+
+    // Transform translations describe a sprite's center, not its corner.
+    let offset_x = left_edge_of_bricks + BRICK_SIZE.x / 2.;
+    let offset_y = bottom_edge_of_bricks + BRICK_SIZE.y / 2.;
+
+    for row in 0..n_rows {
+        for column in 0..n_columns {
+            let brick_position = Vec2::new(
+                offset_x + column as f32 * (BRICK_SIZE.x + GAP_BETWEEN_BRICKS),
+                offset_y + row as f32 * (BRICK_SIZE.y + GAP_BETWEEN_BRICKS),
+            );
+
             commands
                 .spawn()
                 .insert(Brick)
                 .insert_bundle(SpriteBundle {
                     sprite: Sprite {
-                        color: colors[row_y],
+                        color: colors[row % colors.len()],
                         ..default()
                     },
                     transform: Transform {
-                        translation: Vec3::new(
-                            LEFT_WALL + (80. + ((row_x as f32) * 75.)),
-                            TOP_WALL - (80. + row_y as f32 * 30.),
-                            0.0,
-                        ),
+                        translation: brick_position.extend(0.0),
                         scale: Vec3::new(BRICK_SIZE.x, BRICK_SIZE.y, 1.0),
                         ..default()
                     },
@@ -370,6 +438,42 @@ fn handle_waiting_click(
     }
 }
 
+// Clamps the ball's center onto the box to find which side it hit. The outer `Option` is
+// whether the ball touches the box at all; the inner one is `None` when the ball's center is
+// already inside the box, which still counts as a hit but has no side to reflect off of.
+fn ball_collision(
+    ball_center: Vec2,
+    ball_radius: f32,
+    box_center: Vec2,
+    box_half_size: Vec2,
+) -> Option<Option<Collision>> {
+    let box_min = box_center - box_half_size;
+    let box_max = box_center + box_half_size;
+    let closest = ball_center.clamp(box_min, box_max);
+    let offset = ball_center - closest;
+
+    if offset.length() > ball_radius {
+        return None;
+    }
+    if offset == Vec2::ZERO {
+        return Some(None);
+    }
+
+    let side = if offset.x.abs() > offset.y.abs() {
+        if offset.x < 0. {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if offset.y < 0. {
+        Collision::Bottom
+    } else {
+        Collision::Top
+    };
+
+    Some(Some(side))
+}
+
 fn check_for_collisions(
     mut commands: Commands,
     mut game_state: ResMut<GameState>,
@@ -387,20 +491,28 @@ fn check_for_collisions(
     mut collision_events: EventWriter<CollisionEvent>,
 ) {
     let (mut ball_velocity, ball_transform) = ball_query.single_mut();
-    let ball_size = ball_transform.scale.truncate();
+    let ball_center = ball_transform.translation.truncate();
+    let ball_radius = BALL_SIZE.x / 2.0;
 
     for (collider_entity, transform, maybe_brick, maybe_paddle, maybe_bottom_wall) in
         collider_query.iter()
     {
-        let collision = collide(
-            ball_transform.translation,
-            ball_size,
-            transform.translation,
-            transform.scale.truncate(),
+        let collision = ball_collision(
+            ball_center,
+            ball_radius,
+            transform.translation.truncate(),
+            transform.scale.truncate() / 2.0,
         );
         if let Some(collision) = collision {
-            // Sends a collision event so that other systems can react to the collision
-            collision_events.send_default();
+            // Sends a collision event so that other systems (e.g. audio) can react to it
+            let sound_kind = if maybe_brick.is_some() {
+                CollisionSoundKind::Brick
+            } else if maybe_paddle.is_some() {
+                CollisionSoundKind::Paddle
+            } else {
+                CollisionSoundKind::Wall
+            };
+            collision_events.send(CollisionEvent(sound_kind));
 
             // Bricks should be despawned and increment the scoreboard on collision
             if maybe_brick.is_some() {
@@ -422,6 +534,12 @@ fn check_for_collisions(
                 game_state.lives -= 1;
             }
 
+            // The ball's center is already inside the collider (e.g. a fast-moving ball
+            // tunneling into a brick); there's no side to reflect off of, so stop here.
+            let Some(collision) = collision else {
+                continue;
+            };
+
             // reflect the ball when it collides
             let mut reflect_x = false;
             let mut reflect_y = false;
@@ -433,7 +551,6 @@ fn check_for_collisions(
                 Collision::Right => reflect_x = ball_velocity.x < 0.0,
                 Collision::Top => reflect_y = ball_velocity.y < 0.0,
                 Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
-                Collision::Inside => { /* do nothing */ }
             }
 
             // reflect velocity on the x-axis if we hit something on the x-axis
@@ -460,6 +577,30 @@ fn check_for_collisions(
     }
 }
 
+fn play_collision_sounds(
+    mut collision_events: EventReader<CollisionEvent>,
+    audio: Res<Audio>,
+    collision_sounds: Res<CollisionSounds>,
+    ball_query: Query<&Velocity, With<Ball>>,
+) {
+    let ball_speed = ball_query
+        .get_single()
+        .map(|velocity| velocity.length())
+        .unwrap_or(BALL_SPEED);
+    // Let the pitch climb a little as the ball speeds up from brick hits.
+    let pitch = (ball_speed / BALL_SPEED).clamp(0.8, 1.6);
+
+    for CollisionEvent(kind) in collision_events.iter() {
+        let clip = match kind {
+            CollisionSoundKind::Brick => collision_sounds.brick_break.clone(),
+            CollisionSoundKind::Paddle | CollisionSoundKind::Wall => {
+                collision_sounds.bounce.clone()
+            }
+        };
+        audio.play_with_settings(clip, PlaybackSettings::ONCE.with_speed(pitch));
+    }
+}
+
 fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>) {
     for (mut transform, velocity) in query.iter_mut() {
         transform.translation.x += velocity.x * TIME_STEP;
@@ -473,16 +614,95 @@ fn update_scoreboard(game_state: Res<GameState>, mut query: Query<&mut Text, Wit
     text.sections[3].value = format!("{}", game_state.lives);
 }
 
-fn show_game_over(game_state: Res<GameState>, mut query: Query<&mut Text, With<GameOverText>>) {
+fn check_win_condition(bricks: Query<&Brick>, mut app_state: ResMut<State<AppState>>) {
+    if bricks.iter().next().is_none() {
+        // Already-queued transitions (e.g. both conditions firing the same frame) are fine to ignore.
+        let _ = app_state.set(AppState::Won);
+    }
+}
+
+fn check_lose_condition(game_state: Res<GameState>, mut app_state: ResMut<State<AppState>>) {
     if game_state.lives == 0 {
-        let mut text = query.single_mut();
-        text.sections[0].value = format!("Game over!");
+        let _ = app_state.set(AppState::GameOver);
+    }
+}
+
+fn show_end_message(
+    app_state: Res<State<AppState>>,
+    mut query: Query<&mut Text, With<GameOverText>>,
+) {
+    let mut text = query.single_mut();
+    text.sections[0].value = match app_state.current() {
+        AppState::Won => "You win!\nPress Space to restart".to_string(),
+        AppState::GameOver => "Game over!\nPress Space to restart".to_string(),
+        AppState::Playing => String::new(),
+    };
+}
+
+fn handle_restart_input(
+    mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_state: ResMut<GameState>,
+    mut app_state: ResMut<State<AppState>>,
+    balls: Query<Entity, With<Ball>>,
+    bricks: Query<Entity, With<Brick>>,
+    score_text: Query<Entity, With<ScoreText>>,
+    game_over_text: Query<Entity, With<GameOverText>>,
+) {
+    if !(keyboard_input.just_pressed(KeyCode::Space) || mouse_input.just_pressed(MouseButton::Left)) {
+        return;
+    }
+
+    for entity in balls
+        .iter()
+        .chain(bricks.iter())
+        .chain(score_text.iter())
+        .chain(game_over_text.iter())
+    {
+        commands.entity(entity).despawn();
+    }
+
+    *game_state = GameState {
+        score: 0,
+        lives: 3,
+        ball_waiting: true,
+    };
+    spawn_playfield(&mut commands, &asset_server);
+    let _ = app_state.set(AppState::Playing);
+}
+
+// Gates a single system in the fixed-timestep schedule by its position in
+// `debug_stepping::STEPPED_SYSTEMS`; a no-op pass-through with the feature off.
+#[cfg(feature = "debug_stepping")]
+fn physics_run_criteria(
+    index: usize,
+) -> impl FnMut(In<ShouldRun>, Res<Input<KeyCode>>, Res<debug_stepping::SteppingState>) -> ShouldRun {
+    debug_stepping::system_run_criteria(index)
+}
+
+#[cfg(not(feature = "debug_stepping"))]
+fn physics_run_criteria(_index: usize) -> impl FnMut(In<ShouldRun>) -> ShouldRun {
+    |In(should_run): In<ShouldRun>| should_run
+}
+
+// Only lets the fixed-timestep physics set run while the game is actually in progress.
+fn playing_state_criteria(In(should_run): In<ShouldRun>, app_state: Res<State<AppState>>) -> ShouldRun {
+    if !matches!(should_run, ShouldRun::Yes | ShouldRun::YesAndCheckAgain) {
+        return should_run;
+    }
+
+    if *app_state.current() == AppState::Playing {
+        should_run
+    } else {
+        ShouldRun::No
     }
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .insert_resource(GameState {
             score: 0,
             lives: 3,
@@ -491,18 +711,59 @@ fn main() {
         .insert_resource(ClearColor(BACKGROUND_COLOR))
         .add_startup_system(setup)
         .add_event::<CollisionEvent>()
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(check_for_collisions)
-                .with_system(move_paddle.before(check_for_collisions))
-                .with_system(move_paddle_by_mouse.before(check_for_collisions))
-                .with_system(stick_ball_to_paddle.before(check_for_collisions))
-                .with_system(handle_waiting_click.before(check_for_collisions))
-                .with_system(apply_velocity.before(check_for_collisions))
-                .with_system(show_game_over.before(check_for_collisions))
-                .with_system(update_scoreboard.before(check_for_collisions)),
-        )
-        .add_system(bevy::input::system::exit_on_esc_system)
-        .run();
+        .add_state(AppState::Playing);
+
+    // Each physics system gets its own fixed-timestep `SystemSet` so stepping can gate it
+    // independently; `index` must match the system's position in `debug_stepping::STEPPED_SYSTEMS`.
+    macro_rules! add_stepped_system {
+        ($app:expr, $index:expr, $system:expr) => {
+            $app.add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(
+                        FixedTimestep::step(TIME_STEP as f64)
+                            .chain(physics_run_criteria($index))
+                            .chain(playing_state_criteria),
+                    )
+                    .with_system($system),
+            );
+        };
+    }
+    add_stepped_system!(app, 0, move_paddle.before(check_for_collisions));
+    add_stepped_system!(app, 1, move_paddle_by_mouse.before(check_for_collisions));
+    add_stepped_system!(app, 2, stick_ball_to_paddle.before(check_for_collisions));
+    add_stepped_system!(app, 3, handle_waiting_click.before(check_for_collisions));
+    add_stepped_system!(app, 4, apply_velocity.before(check_for_collisions));
+    add_stepped_system!(app, 5, check_for_collisions);
+    add_stepped_system!(app, 6, play_collision_sounds.after(check_for_collisions));
+    add_stepped_system!(app, 7, update_scoreboard.before(check_for_collisions));
+    add_stepped_system!(app, 8, check_win_condition.after(check_for_collisions));
+    add_stepped_system!(app, 9, check_lose_condition.after(check_for_collisions));
+
+    // The only system that mutates the stepping cursor; runs once per tick after every
+    // stepped system so F11 advances exactly one step regardless of criteria evaluation order.
+    #[cfg(feature = "debug_stepping")]
+    app.add_system_set(
+        SystemSet::new()
+            .with_run_criteria(FixedTimestep::step(TIME_STEP as f64).chain(playing_state_criteria))
+            .with_system(
+                debug_stepping::advance_cursor
+                    .after(play_collision_sounds)
+                    .after(check_win_condition)
+                    .after(check_lose_condition),
+            ),
+    );
+
+    app.add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(show_end_message))
+        .add_system_set(SystemSet::on_enter(AppState::Won).with_system(show_end_message))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(handle_restart_input))
+        .add_system_set(SystemSet::on_update(AppState::Won).with_system(handle_restart_input))
+        .add_system(bevy::input::system::exit_on_esc_system);
+
+    #[cfg(feature = "debug_stepping")]
+    app.init_resource::<debug_stepping::SteppingState>()
+        .add_startup_system(debug_stepping::spawn_overlay)
+        .add_system(debug_stepping::handle_input)
+        .add_system(debug_stepping::update_overlay);
+
+    app.run();
 }